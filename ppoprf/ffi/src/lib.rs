@@ -10,16 +10,94 @@ pub struct RandomnessServer {
     inner: ppoprf::Server,
 }
 
-/// Construct a new server instance and return an opaque handle to it.
+/// Result codes returned across the FFI boundary.
+///
+/// Rust panics (e.g. from a malformed argument rejected deep inside
+/// `ppoprf`) are caught at the boundary with `catch_unwind` and mapped
+/// to one of these instead of unwinding into C, which is undefined
+/// behavior.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RandomnessStatus {
+    /// The call completed successfully; any out-parameters are valid.
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullPointer = 1,
+    /// A buffer argument had an unexpected length.
+    BadLength = 2,
+    /// The requested metadata tag has already been punctured.
+    AlreadyPunctured = 3,
+    /// The evaluation failed for a reason not covered above.
+    EvalFailed = 4,
+}
+
+/// A borrowed, length-prefixed buffer passed across the FFI boundary.
+///
+/// Used for metadata tags, which the underlying `ppoprf::Server` takes
+/// as arbitrary byte slices rather than a fixed-width value.
+#[repr(C)]
+pub struct RawBuffer {
+    pub ptr: *const u8,
+    pub len: usize,
+}
+
+/// Construct a new server instance seeded with a single metadata tag
+/// and write an opaque handle to it into `*out`.
 ///
 /// The handle must be freed by calling randomness_server_release().
-// FIXME: Pass a [u8] and length for the md initialization.
 #[no_mangle]
-pub extern "C" fn randomness_server_create() -> *mut RandomnessServer {
-    let test_mds = vec!["t".into()];
-    let inner = ppoprf::Server::new(&test_mds);
-    let server = Box::new(RandomnessServer { inner });
-    Box::into_raw(server)
+pub extern "C" fn randomness_server_create(
+    out: *mut *mut RandomnessServer,
+    md: *const u8,
+    md_len: usize,
+) -> RandomnessStatus {
+    if md.is_null() {
+        return RandomnessStatus::NullPointer;
+    }
+    let tag = RawBuffer {
+        ptr: md,
+        len: md_len,
+    };
+    randomness_server_create_with_mds(out, &tag, 1)
+}
+
+/// Construct a new server instance seeded with `count` metadata tags
+/// and write an opaque handle to it into `*out`.
+///
+/// The handle must be freed by calling randomness_server_release().
+#[no_mangle]
+pub extern "C" fn randomness_server_create_with_mds(
+    out: *mut *mut RandomnessServer,
+    mds: *const RawBuffer,
+    count: usize,
+) -> RandomnessStatus {
+    if out.is_null() || mds.is_null() {
+        return RandomnessStatus::NullPointer;
+    }
+
+    let raw_mds = unsafe { std::slice::from_raw_parts(mds, count) };
+    if raw_mds.iter().any(|buf| buf.ptr.is_null()) {
+        return RandomnessStatus::NullPointer;
+    }
+
+    let result = std::panic::catch_unwind(|| {
+        let tags: Vec<Vec<u8>> = raw_mds
+            .iter()
+            .map(|buf| unsafe { std::slice::from_raw_parts(buf.ptr, buf.len).to_vec() })
+            .collect();
+        let inner = ppoprf::Server::new(&tags);
+        Box::into_raw(Box::new(RandomnessServer { inner }))
+    });
+
+    match result {
+        Ok(ptr) => {
+            unsafe {
+                *out = ptr;
+            }
+            RandomnessStatus::Ok
+        }
+        Err(_) => RandomnessStatus::EvalFailed,
+    }
 }
 
 /// Release memory associated with a server instance.
@@ -34,6 +112,13 @@ pub extern "C" fn randomness_server_release(ptr: *mut RandomnessServer) {
 }
 
 /// Evaluate the PPOPRF for the given point.
+///
+/// If `verifiable` is true and the evaluation carries a proof, it is
+/// serialized into `proof_buf` (whose capacity is `proof_buf_len`) and
+/// its length is written to `*proof_len_out`. Pass a null `proof_buf`
+/// to query the required length, without copying anything, via
+/// `*proof_len_out`; either pointer may be null if the proof isn't
+/// needed.
 #[no_mangle]
 pub extern "C" fn randomness_server_eval(
     ptr: *const RandomnessServer,
@@ -41,44 +126,251 @@ pub extern "C" fn randomness_server_eval(
     md_index: usize,
     verifiable: bool,
     output: *mut u8,
-) {
+    proof_buf: *mut u8,
+    proof_buf_len: usize,
+    proof_len_out: *mut usize,
+) -> RandomnessStatus {
     // Verify arguments.
-    assert!(!ptr.is_null());
-    assert!(!input.is_null());
-    assert!(!output.is_null());
-
-    // Convert our *const argument to a &ppoprf::Server without taking ownership.
-    let server = unsafe { &(*ptr).inner };
-    // Wrap the provided compressed Ristretto point in the expected type.
-    // Unfortunately from_slice() copies the data here.
-    let point = unsafe {
-        let bytes = std::slice::from_raw_parts(input, ppoprf::COMPRESSED_POINT_LEN);
-        ppoprf::CompressedRistretto::from_slice(bytes)
+    if ptr.is_null() || input.is_null() || output.is_null() {
+        return RandomnessStatus::NullPointer;
+    }
+
+    let result = std::panic::catch_unwind(|| {
+        // Convert our *const argument to a &ppoprf::Server without taking ownership.
+        let server = unsafe { &(*ptr).inner };
+        // Wrap the provided compressed Ristretto point in the expected type.
+        // Unfortunately from_slice() copies the data here.
+        let point = unsafe {
+            let bytes = std::slice::from_raw_parts(input, ppoprf::COMPRESSED_POINT_LEN);
+            ppoprf::CompressedRistretto::from_slice(bytes)
+        };
+        // Evaluate the requested point.
+        let evaluation = server.eval(&point, md_index, verifiable);
+        // Copy the resulting point into the output buffer.
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                evaluation.output.as_bytes().as_ptr(),
+                output,
+                ppoprf::COMPRESSED_POINT_LEN,
+            );
+        }
+        evaluation.proof.map(|proof| proof.to_bytes())
+    });
+
+    let proof_bytes = match result {
+        Ok(proof_bytes) => proof_bytes,
+        Err(_) => return RandomnessStatus::EvalFailed,
     };
-    // Evaluate the requested point.
-    let result = server.eval(&point, md_index, verifiable);
-    // Copy the resulting point into the output buffer.
-    unsafe {
-        std::ptr::copy_nonoverlapping(
-            result.output.as_bytes().as_ptr(),
-            output,
-            ppoprf::COMPRESSED_POINT_LEN,
-        );
+
+    match proof_bytes {
+        Some(proof_bytes) => {
+            if !proof_len_out.is_null() {
+                unsafe {
+                    *proof_len_out = proof_bytes.len();
+                }
+            }
+            if !proof_buf.is_null() {
+                if proof_buf_len < proof_bytes.len() {
+                    return RandomnessStatus::BadLength;
+                }
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        proof_bytes.as_ptr(),
+                        proof_buf,
+                        proof_bytes.len(),
+                    );
+                }
+            }
+        }
+        None => {
+            if !proof_len_out.is_null() {
+                unsafe {
+                    *proof_len_out = 0;
+                }
+            }
+        }
     }
+
+    RandomnessStatus::Ok
+}
+
+/// Evaluate the PPOPRF for `count` points in a single call.
+///
+/// `inputs` and `outputs` are contiguous arrays of `count` compressed
+/// Ristretto points (`ppoprf::COMPRESSED_POINT_LEN` bytes each), all
+/// evaluated against the same `md_index`. This amortizes the per-call
+/// FFI overhead of `randomness_server_eval` across a batch of points.
+///
+/// If `verifiable` is true and `proofs` is non-null, it must be a
+/// contiguous array of `count` slots of `proof_stride` bytes each,
+/// large enough to hold every serialized proof. Pass a null `proofs`
+/// (with `proof_stride` ignored) to evaluate verifiably without
+/// collecting proofs.
+#[no_mangle]
+pub extern "C" fn randomness_server_eval_batch(
+    ptr: *const RandomnessServer,
+    inputs: *const u8,
+    count: usize,
+    md_index: usize,
+    verifiable: bool,
+    outputs: *mut u8,
+    proofs: *mut u8,
+    proof_stride: usize,
+) -> RandomnessStatus {
+    if ptr.is_null() || inputs.is_null() || outputs.is_null() {
+        return RandomnessStatus::NullPointer;
+    }
+
+    let result = std::panic::catch_unwind(|| -> Result<(), RandomnessStatus> {
+        let server = unsafe { &(*ptr).inner };
+        let input_bytes =
+            unsafe { std::slice::from_raw_parts(inputs, count * ppoprf::COMPRESSED_POINT_LEN) };
+
+        for i in 0..count {
+            let point_bytes = &input_bytes
+                [i * ppoprf::COMPRESSED_POINT_LEN..(i + 1) * ppoprf::COMPRESSED_POINT_LEN];
+            let point = ppoprf::CompressedRistretto::from_slice(point_bytes);
+            let evaluation = server.eval(&point, md_index, verifiable);
+
+            let output_slot = unsafe {
+                std::slice::from_raw_parts_mut(
+                    outputs.add(i * ppoprf::COMPRESSED_POINT_LEN),
+                    ppoprf::COMPRESSED_POINT_LEN,
+                )
+            };
+            output_slot.copy_from_slice(evaluation.output.as_bytes());
+
+            if !proofs.is_null() {
+                if let Some(proof) = evaluation.proof {
+                    let proof_bytes = proof.to_bytes();
+                    if proof_bytes.len() > proof_stride {
+                        return Err(RandomnessStatus::BadLength);
+                    }
+                    let proof_slot = unsafe {
+                        std::slice::from_raw_parts_mut(
+                            proofs.add(i * proof_stride),
+                            proof_bytes.len(),
+                        )
+                    };
+                    proof_slot.copy_from_slice(&proof_bytes);
+                }
+            }
+        }
+
+        Ok(())
+    });
+
+    match result {
+        Ok(Ok(())) => RandomnessStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => RandomnessStatus::EvalFailed,
+    }
+}
+
+/// Write this server's public key, a compressed Ristretto point, into
+/// `output`, which must have room for `ppoprf::COMPRESSED_POINT_LEN`
+/// bytes.
+#[no_mangle]
+pub extern "C" fn randomness_server_get_public_key(
+    ptr: *const RandomnessServer,
+    output: *mut u8,
+) -> RandomnessStatus {
+    if ptr.is_null() || output.is_null() {
+        return RandomnessStatus::NullPointer;
+    }
+
+    let result = std::panic::catch_unwind(|| {
+        let server = unsafe { &(*ptr).inner };
+        let pubkey = server.get_public_key();
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                pubkey.as_bytes().as_ptr(),
+                output,
+                ppoprf::COMPRESSED_POINT_LEN,
+            );
+        }
+    });
+
+    match result {
+        Ok(()) => RandomnessStatus::Ok,
+        Err(_) => RandomnessStatus::EvalFailed,
+    }
+}
+
+/// Verify a verifiable evaluation without a round trip to the server.
+///
+/// `pubkey`, `input`, and `output` are each a `COMPRESSED_POINT_LEN`-byte
+/// compressed Ristretto point; `proof` is `proof_len` bytes of a
+/// serialized proof as produced by `randomness_server_eval`.
+#[no_mangle]
+pub extern "C" fn randomness_verify(
+    pubkey: *const u8,
+    input: *const u8,
+    output: *const u8,
+    proof: *const u8,
+    proof_len: usize,
+) -> bool {
+    if pubkey.is_null() || input.is_null() || output.is_null() || proof.is_null() {
+        return false;
+    }
+
+    std::panic::catch_unwind(|| {
+        let pubkey_point = unsafe {
+            let bytes = std::slice::from_raw_parts(pubkey, ppoprf::COMPRESSED_POINT_LEN);
+            ppoprf::CompressedRistretto::from_slice(bytes)
+        };
+        let input_point = unsafe {
+            let bytes = std::slice::from_raw_parts(input, ppoprf::COMPRESSED_POINT_LEN);
+            ppoprf::CompressedRistretto::from_slice(bytes)
+        };
+        let output_point = unsafe {
+            let bytes = std::slice::from_raw_parts(output, ppoprf::COMPRESSED_POINT_LEN);
+            ppoprf::CompressedRistretto::from_slice(bytes)
+        };
+        let proof_bytes = unsafe { std::slice::from_raw_parts(proof, proof_len) };
+        let proof = match ppoprf::Proof::from_bytes(proof_bytes) {
+            Some(proof) => proof,
+            None => return false,
+        };
+        ppoprf::verify(&pubkey_point, &input_point, &output_point, &proof)
+    })
+    .unwrap_or(false)
 }
 
 /// Puncture the given md value from the PPOPRF.
 #[no_mangle]
-pub extern "C" fn randomness_server_puncture(ptr: *mut RandomnessServer, md: u8) {
+pub extern "C" fn randomness_server_puncture(
+    ptr: *mut RandomnessServer,
+    md: *const u8,
+    md_len: usize,
+) -> RandomnessStatus {
     // Convert our *const to a &ppoprf::Server without taking ownership.
-    assert!(!ptr.is_null());
-    let server = unsafe { &mut (*ptr).inner };
+    if ptr.is_null() || md.is_null() {
+        return RandomnessStatus::NullPointer;
+    }
 
-    // The ffi signature takes a u8 by value, but the underlying
-    // api wants a slice to allow more than 8 bits of metadata tag.
-    let md_vec = vec![md];
-    // Call correct function.
-    server.puncture(&md_vec);
+    let result = std::panic::catch_unwind(|| {
+        let server = unsafe { &mut (*ptr).inner };
+        let md_vec = unsafe { std::slice::from_raw_parts(md, md_len) }.to_vec();
+        // Call correct function.
+        server.puncture(&md_vec);
+    });
+
+    match result {
+        Ok(()) => RandomnessStatus::Ok,
+        // `Server::puncture` signals failure by panicking rather than
+        // returning a `Result`. Rather than string-matching a
+        // `Debug`-formatted message (which would silently stop working
+        // the moment that wording changes upstream), downcast the
+        // panic payload against the concrete `ggm::GGMError` the
+        // puncture path panics with; anything we don't recognize is
+        // reported as a generic failure rather than misreported as
+        // AlreadyPunctured.
+        Err(payload) => match payload.downcast_ref::<ppoprf::ggm::GGMError>() {
+            Some(ppoprf::ggm::GGMError::AlreadyPunctured) => RandomnessStatus::AlreadyPunctured,
+            _ => RandomnessStatus::EvalFailed,
+        },
+    }
 }
 
 #[cfg(test)]
@@ -91,31 +383,283 @@ mod tests {
     use crate::*;
     use curve25519_dalek::ristretto::CompressedRistretto;
 
+    /// Create a server instance seeded with the single metadata tag "t".
+    fn create_test_server() -> *mut RandomnessServer {
+        let mut server = std::ptr::null_mut();
+        let md = b"t";
+        assert_eq!(
+            randomness_server_create(&mut server, md.as_ptr(), md.len()),
+            RandomnessStatus::Ok
+        );
+        server
+    }
+
     #[test]
     /// Verify creation/release of the opaque server handle.
     fn unused_instance() {
-        let server = randomness_server_create();
+        let server = create_test_server();
         assert!(!server.is_null());
         randomness_server_release(server);
     }
 
+    #[test]
+    /// randomness_server_create() rejects a null out-parameter.
+    fn create_null_out_param() {
+        let md = b"t";
+        assert_eq!(
+            randomness_server_create(std::ptr::null_mut(), md.as_ptr(), md.len()),
+            RandomnessStatus::NullPointer
+        );
+    }
+
     #[test]
     /// One evaluation call to the ppoprf.
     fn simple_eval() {
-        let server = randomness_server_create();
+        let server = create_test_server();
         assert!(!server.is_null());
 
         // Evaluate a test point.
         let point = CompressedRistretto::default();
         let mut result = Vec::with_capacity(ppoprf::COMPRESSED_POINT_LEN);
-        randomness_server_eval(
+        let status = randomness_server_eval(
             server,
             point.as_bytes().as_ptr(),
             0,
             false,
             result.as_mut_ptr(),
+            std::ptr::null_mut(),
+            0,
+            std::ptr::null_mut(),
+        );
+        assert_eq!(status, RandomnessStatus::Ok);
+        randomness_server_release(server);
+    }
+
+    #[test]
+    /// Passing null pointers to randomness_server_eval() is reported,
+    /// not a crash across the FFI boundary.
+    fn eval_null_pointer() {
+        let status = randomness_server_eval(
+            std::ptr::null(),
+            std::ptr::null(),
+            0,
+            false,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            0,
+            std::ptr::null_mut(),
+        );
+        assert_eq!(status, RandomnessStatus::NullPointer);
+    }
+
+    #[test]
+    /// Perform a verifiable evaluation, then check the proof via
+    /// randomness_verify() without going back to the server.
+    fn eval_then_verify() {
+        let server = create_test_server();
+        assert!(!server.is_null());
+
+        let point = CompressedRistretto::default();
+        let mut output = vec![0u8; ppoprf::COMPRESSED_POINT_LEN];
+
+        // First call with a null proof buffer to learn how big it needs to be.
+        let mut proof_len = 0usize;
+        let status = randomness_server_eval(
+            server,
+            point.as_bytes().as_ptr(),
+            0,
+            true,
+            output.as_mut_ptr(),
+            std::ptr::null_mut(),
+            0,
+            &mut proof_len,
         );
-        // FIXME: verify result!
+        assert_eq!(status, RandomnessStatus::Ok);
+        assert!(proof_len > 0);
+
+        let mut proof = vec![0u8; proof_len];
+        let status = randomness_server_eval(
+            server,
+            point.as_bytes().as_ptr(),
+            0,
+            true,
+            output.as_mut_ptr(),
+            proof.as_mut_ptr(),
+            proof.len(),
+            &mut proof_len,
+        );
+        assert_eq!(status, RandomnessStatus::Ok);
+
+        let mut pubkey = vec![0u8; ppoprf::COMPRESSED_POINT_LEN];
+        let status = randomness_server_get_public_key(server, pubkey.as_mut_ptr());
+        assert_eq!(status, RandomnessStatus::Ok);
+
+        assert!(randomness_verify(
+            pubkey.as_ptr(),
+            point.as_bytes().as_ptr(),
+            output.as_ptr(),
+            proof.as_ptr(),
+            proof.len(),
+        ));
+
+        randomness_server_release(server);
+    }
+
+    #[test]
+    /// A batch evaluation of several points matches evaluating each
+    /// one individually.
+    fn eval_batch_matches_individual_eval() {
+        let server = create_test_server();
+        assert!(!server.is_null());
+
+        let points = [
+            CompressedRistretto::default(),
+            CompressedRistretto::default(),
+            CompressedRistretto::default(),
+        ];
+        let mut inputs = Vec::with_capacity(points.len() * ppoprf::COMPRESSED_POINT_LEN);
+        for point in &points {
+            inputs.extend_from_slice(point.as_bytes());
+        }
+
+        let mut batch_outputs = vec![0u8; points.len() * ppoprf::COMPRESSED_POINT_LEN];
+        let status = randomness_server_eval_batch(
+            server,
+            inputs.as_ptr(),
+            points.len(),
+            0,
+            false,
+            batch_outputs.as_mut_ptr(),
+            std::ptr::null_mut(),
+            0,
+        );
+        assert_eq!(status, RandomnessStatus::Ok);
+
+        for (i, point) in points.iter().enumerate() {
+            let mut single_output = vec![0u8; ppoprf::COMPRESSED_POINT_LEN];
+            let status = randomness_server_eval(
+                server,
+                point.as_bytes().as_ptr(),
+                0,
+                false,
+                single_output.as_mut_ptr(),
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+            );
+            assert_eq!(status, RandomnessStatus::Ok);
+
+            let batch_slot = &batch_outputs
+                [i * ppoprf::COMPRESSED_POINT_LEN..(i + 1) * ppoprf::COMPRESSED_POINT_LEN];
+            assert_eq!(batch_slot, single_output.as_slice());
+        }
+
+        randomness_server_release(server);
+    }
+
+    #[test]
+    /// Passing null pointers to randomness_server_eval_batch() is
+    /// reported, not a crash across the FFI boundary.
+    fn eval_batch_null_pointer() {
+        let status = randomness_server_eval_batch(
+            std::ptr::null(),
+            std::ptr::null(),
+            0,
+            0,
+            false,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            0,
+        );
+        assert_eq!(status, RandomnessStatus::NullPointer);
+    }
+
+    #[test]
+    /// A verifiable batch evaluation with a null `proofs` buffer still
+    /// succeeds; it just doesn't collect proofs.
+    fn eval_batch_verifiable_without_collecting_proofs() {
+        let server = create_test_server();
+        assert!(!server.is_null());
+
+        let point = CompressedRistretto::default();
+        let mut output = vec![0u8; ppoprf::COMPRESSED_POINT_LEN];
+        let status = randomness_server_eval_batch(
+            server,
+            point.as_bytes().as_ptr(),
+            1,
+            0,
+            true,
+            output.as_mut_ptr(),
+            std::ptr::null_mut(),
+            0,
+        );
+        assert_eq!(status, RandomnessStatus::Ok);
+
+        randomness_server_release(server);
+    }
+
+    #[test]
+    /// Create a server with several metadata tags and puncture one of
+    /// them by its arbitrary-length tag bytes.
+    fn create_with_mds_and_puncture() {
+        let tags: [&[u8]; 2] = [b"first-tag", b"second-metadata-tag"];
+        let raw_tags: Vec<RawBuffer> = tags
+            .iter()
+            .map(|t| RawBuffer {
+                ptr: t.as_ptr(),
+                len: t.len(),
+            })
+            .collect();
+
+        let mut server = std::ptr::null_mut();
+        assert_eq!(
+            randomness_server_create_with_mds(&mut server, raw_tags.as_ptr(), raw_tags.len()),
+            RandomnessStatus::Ok
+        );
+        assert!(!server.is_null());
+
+        let status = randomness_server_puncture(server, tags[0].as_ptr(), tags[0].len());
+        assert_eq!(status, RandomnessStatus::Ok);
+
+        randomness_server_release(server);
+    }
+
+    #[test]
+    /// Passing a null md pointer to randomness_server_puncture() is
+    /// reported, not a crash across the FFI boundary.
+    fn puncture_null_pointer() {
+        let server = create_test_server();
+        let status = randomness_server_puncture(server, std::ptr::null(), 0);
+        assert_eq!(status, RandomnessStatus::NullPointer);
+        randomness_server_release(server);
+    }
+
+    #[test]
+    /// Puncturing the same md twice is reported as AlreadyPunctured,
+    /// not a generic failure.
+    fn puncture_twice_is_already_punctured() {
+        let server = create_test_server();
+        let md = b"t";
+
+        let status = randomness_server_puncture(server, md.as_ptr(), md.len());
+        assert_eq!(status, RandomnessStatus::Ok);
+
+        let status = randomness_server_puncture(server, md.as_ptr(), md.len());
+        assert_eq!(status, RandomnessStatus::AlreadyPunctured);
+
+        randomness_server_release(server);
+    }
+
+    #[test]
+    /// Puncturing an md that was never registered with the server is a
+    /// generic failure, not misreported as AlreadyPunctured.
+    fn puncture_unknown_md_is_eval_failed() {
+        let server = create_test_server();
+        let md = b"never-registered";
+
+        let status = randomness_server_puncture(server, md.as_ptr(), md.len());
+        assert_eq!(status, RandomnessStatus::EvalFailed);
+
         randomness_server_release(server);
     }
 
@@ -3,6 +3,7 @@
 //! with extended functionality that allows puncturing inputs from
 //! secret keys.
 
+use std::convert::TryInto;
 use std::fmt;
 
 use super::PPRF;
@@ -12,12 +13,44 @@ use ring::{
     rand::{SecureRandom, SystemRandom},
 };
 
+/// Number of bytes in an HMAC-SHA256 output, i.e. the length of every
+/// seed and PRG secret stored in a `GGMPuncturableKey`.
+const HMAC_SHA256_OUTPUT_LEN: usize = 32;
+
+/// Wire format version for `GGM::to_bytes`/`GGM::from_bytes`. Bump this
+/// whenever the on-disk/on-wire layout changes so old and new binaries
+/// can detect an incompatible key rather than silently misparsing it.
+const GGM_KEY_WIRE_VERSION: u8 = 1;
+
+/// A `GGMPuncturableKey` always has exactly two PRGs: one for the left
+/// child and one for the right child at every level of the tree.
+const GGM_NUM_PRGS: usize = 2;
+
+/// Errors returned by GGM key operations, including parsing a key
+/// serialized with `GGM::to_bytes`.
 #[derive(Debug)]
-enum GGMError {
+pub enum GGMError {
     NoPrefixFound,
     AlreadyPunctured,
+    /// The byte buffer was truncated, used an unrecognized wire
+    /// version, or otherwise didn't match the expected layout.
+    InvalidFormat,
+    /// The surviving prefixes in a deserialized key don't form a
+    /// disjoint cover of the domain (one is a prefix of another).
+    OverlappingPrefixes,
+    /// A seed or PRG secret's length didn't match the HMAC-SHA256
+    /// output size.
+    InvalidSeedLength,
+}
+
+impl fmt::Display for GGMError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
 }
 
+impl std::error::Error for GGMError {}
+
 #[derive(Clone, Eq, PartialEq)]
 struct Prefix {
     bits: BitVec<bitvec::order::Lsb0, usize>,
@@ -31,6 +64,47 @@ impl Prefix {
     fn len(&self) -> usize {
         self.bits.len()
     }
+
+    /// Append this prefix's wire encoding (bit length followed by the
+    /// bits packed LSB-first into bytes) to `out`.
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.bits.len() as u32).to_le_bytes());
+        let mut packed = vec![0u8; (self.bits.len() + 7) / 8];
+        for (i, bit) in self.bits.iter().enumerate() {
+            if *bit {
+                packed[i / 8] |= 1 << (i % 8);
+            }
+        }
+        out.extend_from_slice(&packed);
+    }
+
+    /// Parse a prefix previously written by `write_bytes`, advancing
+    /// `buf` past the bytes consumed.
+    fn read_bytes(buf: &mut &[u8]) -> Result<Self, GGMError> {
+        let bit_len = read_u32(buf)? as usize;
+        let byte_len = (bit_len + 7) / 8;
+        if buf.len() < byte_len {
+            return Err(GGMError::InvalidFormat);
+        }
+        let (packed, rest) = buf.split_at(byte_len);
+        *buf = rest;
+        let mut bits: BitVec<bitvec::order::Lsb0, usize> = BitVec::with_capacity(bit_len);
+        for i in 0..bit_len {
+            bits.push(packed[i / 8] & (1 << (i % 8)) != 0);
+        }
+        Ok(Prefix::new(bits))
+    }
+}
+
+/// Read a little-endian `u32` off the front of `buf`, advancing it past
+/// the bytes consumed.
+fn read_u32(buf: &mut &[u8]) -> Result<u32, GGMError> {
+    if buf.len() < 4 {
+        return Err(GGMError::InvalidFormat);
+    }
+    let (head, rest) = buf.split_at(4);
+    *buf = rest;
+    Ok(u32::from_le_bytes(head.try_into().unwrap()))
 }
 
 impl fmt::Debug for Prefix {
@@ -39,20 +113,24 @@ impl fmt::Debug for Prefix {
     }
 }
 
+// The HMAC key is kept as raw bytes, rather than a pre-built
+// `ring::hmac::Key`, so that a punctured key can be serialized and
+// handed to another party for local evaluation (see `to_bytes`).
 #[derive(Clone)]
 struct GGMPseudorandomGenerator {
-    key: ring::hmac::Key,
+    secret: Vec<u8>,
 }
 
 impl GGMPseudorandomGenerator {
     fn setup() -> Self {
-        let secret = sample_secret();
-        let s_key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_ref());
-        GGMPseudorandomGenerator { key: s_key }
+        GGMPseudorandomGenerator {
+            secret: sample_secret(),
+        }
     }
 
     fn eval(&self, input: &[u8], output: &mut [u8]) {
-        let tag = hmac::sign(&self.key, input);
+        let key = hmac::Key::new(hmac::HMAC_SHA256, &self.secret);
+        let tag = hmac::sign(&key, input);
         output.copy_from_slice(tag.as_ref());
     }
 }
@@ -115,6 +193,98 @@ impl GGMPuncturableKey {
         }
         Err(GGMError::NoPrefixFound)
     }
+
+    /// Encode the PRGs, surviving `(Prefix, seed)` pairs and punctured
+    /// prefixes so this key can be shipped to another party for local
+    /// evaluation.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        out.extend_from_slice(&(self.prgs.len() as u32).to_le_bytes());
+        for prg in &self.prgs {
+            out.extend_from_slice(&(prg.secret.len() as u32).to_le_bytes());
+            out.extend_from_slice(&prg.secret);
+        }
+
+        out.extend_from_slice(&(self.prefixes.len() as u32).to_le_bytes());
+        for (pfx, seed) in &self.prefixes {
+            pfx.write_bytes(&mut out);
+            out.extend_from_slice(&(seed.len() as u32).to_le_bytes());
+            out.extend_from_slice(seed);
+        }
+
+        out.extend_from_slice(&(self.punctured.len() as u32).to_le_bytes());
+        for pfx in &self.punctured {
+            pfx.write_bytes(&mut out);
+        }
+
+        out
+    }
+
+    /// Parse a key previously written by `to_bytes`, advancing `buf`
+    /// past the bytes consumed. Rejects keys whose surviving prefixes
+    /// overlap (so they no longer form a disjoint cover) or whose seeds
+    /// don't match the HMAC-SHA256 output size.
+    fn from_bytes(buf: &mut &[u8]) -> Result<Self, GGMError> {
+        let num_prgs = read_u32(buf)? as usize;
+        if num_prgs != GGM_NUM_PRGS {
+            return Err(GGMError::InvalidFormat);
+        }
+        let mut prgs = Vec::with_capacity(num_prgs);
+        for _ in 0..num_prgs {
+            let secret = read_sized_bytes(buf)?;
+            prgs.push(GGMPseudorandomGenerator { secret });
+        }
+
+        // `num_prefixes` is untrusted input and each entry consumes at
+        // least a few bytes of `buf`, so build the `Vec` incrementally
+        // rather than preallocating for an attacker-chosen count.
+        let num_prefixes = read_u32(buf)? as usize;
+        let mut prefixes = Vec::new();
+        for _ in 0..num_prefixes {
+            let pfx = Prefix::read_bytes(buf)?;
+            let seed = read_sized_bytes(buf)?;
+            prefixes.push((pfx, seed));
+        }
+        for i in 0..prefixes.len() {
+            for j in (i + 1)..prefixes.len() {
+                let (a, b) = (&prefixes[i].0.bits, &prefixes[j].0.bits);
+                if a.starts_with(b) || b.starts_with(a) {
+                    return Err(GGMError::OverlappingPrefixes);
+                }
+            }
+        }
+
+        // Same reasoning as `prefixes` above: don't preallocate off an
+        // untrusted count.
+        let num_punctured = read_u32(buf)? as usize;
+        let mut punctured = Vec::new();
+        for _ in 0..num_punctured {
+            punctured.push(Prefix::read_bytes(buf)?);
+        }
+
+        Ok(GGMPuncturableKey {
+            prgs,
+            prefixes,
+            punctured,
+        })
+    }
+}
+
+/// Read a length-prefixed byte buffer, rejecting lengths that don't
+/// match an HMAC-SHA256 output (the only size seeds and PRG secrets
+/// ever take in this construction).
+fn read_sized_bytes(buf: &mut &[u8]) -> Result<Vec<u8>, GGMError> {
+    let len = read_u32(buf)? as usize;
+    if len != HMAC_SHA256_OUTPUT_LEN {
+        return Err(GGMError::InvalidSeedLength);
+    }
+    if buf.len() < len {
+        return Err(GGMError::InvalidFormat);
+    }
+    let (bytes, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(bytes.to_vec())
 }
 
 #[derive(Clone)]
@@ -149,14 +319,72 @@ impl GGM {
     }
 }
 
-impl PPRF for GGM {
-    fn setup() -> Self {
+impl GGM {
+    /// Construct a GGM PPRF whose domain is `bytes` bytes wide, i.e. a
+    /// tree of depth `8 * bytes`, instead of the single-byte domain
+    /// used by `PPRF::setup`. `eval`/`puncture` inputs must then be
+    /// exactly `bytes` bytes long.
+    pub fn setup_with_input_len(bytes: usize) -> Self {
+        assert!(bytes > 0, "input length must be at least one byte");
         GGM {
-            inp_len: 1,
+            inp_len: bytes,
             key: GGMPuncturableKey::new(),
         }
     }
 
+    /// Serialize this key (punctured or not) to a stable, versioned
+    /// wire format so it can be shipped to another party for local
+    /// evaluation via `from_bytes`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![GGM_KEY_WIRE_VERSION];
+        out.extend_from_slice(&(self.inp_len as u32).to_le_bytes());
+        out.extend(self.key.to_bytes());
+        out
+    }
+
+    /// Parse a key previously written by `to_bytes`. Rejects an
+    /// unrecognized wire version, truncated input, overlapping
+    /// prefixes, or seeds that don't match the HMAC-SHA256 output
+    /// size, any of which would otherwise risk silently producing
+    /// wrong evaluations.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GGMError> {
+        let mut buf = bytes;
+        if buf.is_empty() || buf[0] != GGM_KEY_WIRE_VERSION {
+            return Err(GGMError::InvalidFormat);
+        }
+        buf = &buf[1..];
+        let inp_len = read_u32(&mut buf)? as usize;
+        let key = GGMPuncturableKey::from_bytes(&mut buf)?;
+        Ok(GGM { inp_len, key })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for GGM {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_bytes(&self.to_bytes())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for GGM {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let bytes = <Vec<u8> as serde::Deserialize>::deserialize(deserializer)?;
+        GGM::from_bytes(&bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+impl PPRF for GGM {
+    fn setup() -> Self {
+        GGM::setup_with_input_len(1)
+    }
+
     fn eval(&self, input: &[u8], output: &mut [u8]) {
         if input.len() != self.inp_len {
             panic!(
@@ -209,7 +437,12 @@ impl PPRF for GGM {
             }
 
             if let Err(e) = self.key.puncture(&pfx.0, &Prefix::new(bv), new_pfxs) {
-                panic!("Problem puncturing key: {:?}", e);
+                // Unwind with the `GGMError` itself, rather than a
+                // `Debug`-formatted string, so callers that catch the
+                // unwind (e.g. the FFI layer) can downcast the payload
+                // back to a concrete error instead of string-matching
+                // wording this module doesn't promise to keep stable.
+                std::panic::resume_unwind(Box::new(e));
             }
         } else {
             panic!("No prefix found");
@@ -342,6 +575,97 @@ mod tests {
         }
     }
 
+    #[test]
+    fn eval_multi_byte_input() {
+        let ggm = GGM::setup_with_input_len(2);
+        let x0 = [8u8, 1u8];
+        let x1 = [7u8, 200u8];
+        let mut out = [0u8; 32];
+        ggm.eval(&x0, &mut out);
+        ggm.eval(&x1, &mut out);
+    }
+
+    #[test]
+    #[should_panic(expected = "NoPrefixFound")]
+    fn puncture_multi_byte_input() {
+        let mut ggm = GGM::setup_with_input_len(2);
+        let x0 = [8u8, 1u8];
+        let mut out = [0u8; 32];
+        ggm.eval(&x0, &mut out);
+        ggm.puncture(&x0);
+        // next step should panic
+        ggm.eval(&x0, &mut out);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not match input param")]
+    fn eval_wrong_input_len_panics() {
+        let ggm = GGM::setup_with_input_len(2);
+        let x0 = [8u8];
+        let mut out = [0u8; 32];
+        ggm.eval(&x0, &mut out);
+    }
+
+    #[test]
+    fn serialize_roundtrip_matches_eval() {
+        let mut ggm = GGM::setup();
+        let x0 = [8u8];
+        let x1 = [7u8];
+        ggm.puncture(&x1);
+
+        let bytes = ggm.to_bytes();
+        let restored = GGM::from_bytes(&bytes).expect("valid key should parse");
+
+        let mut out_before = [0u8; 32];
+        let mut out_after = [0u8; 32];
+        ggm.eval(&x0, &mut out_before);
+        restored.eval(&x0, &mut out_after);
+        assert_eq!(out_before, out_after);
+    }
+
+    #[test]
+    fn from_bytes_rejects_truncated_input() {
+        let ggm = GGM::setup();
+        let mut bytes = ggm.to_bytes();
+        bytes.truncate(bytes.len() - 1);
+        assert!(GGM::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn from_bytes_rejects_bad_version() {
+        let ggm = GGM::setup();
+        let mut bytes = ggm.to_bytes();
+        bytes[0] = 0xff;
+        assert!(GGM::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn from_bytes_rejects_wrong_prg_count() {
+        let ggm = GGM::setup();
+        let mut bytes = ggm.to_bytes();
+        // Layout: [version: u8][inp_len: u32][num_prgs: u32][...].
+        // Corrupt num_prgs (the first u32 after inp_len) from 2 to 1.
+        let num_prgs_offset = 1 + 4;
+        bytes[num_prgs_offset..num_prgs_offset + 4].copy_from_slice(&1u32.to_le_bytes());
+        assert!(GGM::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn from_bytes_rejects_oversized_prefix_count_without_huge_alloc() {
+        let ggm = GGM::setup();
+        let mut bytes = ggm.to_bytes();
+        // Layout: [version: u8][inp_len: u32][num_prgs: u32]
+        //         [(secret_len: u32, secret: [u8; 32]); num_prgs][num_prefixes: u32][...].
+        let secret_entry_len = 4 + HMAC_SHA256_OUTPUT_LEN;
+        let num_prefixes_offset = 1 + 4 + 4 + GGM_NUM_PRGS * secret_entry_len;
+        bytes[num_prefixes_offset..num_prefixes_offset + 4]
+            .copy_from_slice(&u32::MAX.to_le_bytes());
+        // This must fail because the buffer doesn't actually contain
+        // u32::MAX prefixes, not abort the process trying to
+        // preallocate a multi-gigabyte Vec for an attacker-chosen count.
+        assert!(GGM::from_bytes(&bytes).is_err());
+    }
+
     #[test]
     fn casting() {
         let bv_0 = bits![0].to_bitvec();